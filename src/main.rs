@@ -2,8 +2,9 @@ mod fps;
 
 use bevy::{prelude::*, time::common_conditions::on_timer, utils::info};
 use fps::FpsPlugin;
-use rand::seq::IteratorRandom;
+use rand::{seq::IteratorRandom, Rng};
 use std::{
+    collections::VecDeque,
     ops::{Deref, DerefMut},
     time::Duration,
 };
@@ -13,6 +14,11 @@ const ARENA_HEIGHT: i32 = 20;
 const SNAKE_HEAD_COLOR: Color = Color::srgb(0.7, 0.7, 0.7);
 const SNAKE_SEGMENT_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
 const FOOD_COLOR: Color = Color::srgb(1.0, 0.0, 1.0);
+const GOLDEN_FOOD_COLOR: Color = Color::srgb(1.0, 0.84, 0.0);
+// 金色食物出现的概率（1/N）、额外生长段数和存活秒数
+const GOLDEN_FOOD_ODDS: u32 = 4;
+const GOLDEN_FOOD_GROWTH: u32 = 3;
+const GOLDEN_FOOD_LIFETIME_SECS: f32 = 5.0;
 
 #[derive(Component, Clone, Copy, Debug, PartialEq)]
 struct Position {
@@ -70,6 +76,13 @@ fn spawn_camera(mut commands: Commands) {
 #[derive(Component)]
 struct Food;
 
+// 金色食物：稀有、限时、吃到额外加成
+#[derive(Component)]
+struct GoldenFood;
+
+#[derive(Component)]
+struct Lifetime(Timer);
+
 fn all_position() -> Vec<Position> {
     let mut positions = Vec::with_capacity((ARENA_WIDTH * ARENA_HEIGHT) as usize);
     for x in 0..ARENA_WIDTH {
@@ -85,13 +98,18 @@ fn all_position() -> Vec<Position> {
 }
 
 fn spawn_food(mut commands: Commands, positions: Query<&Position>) {
+    let mut rng = rand::thread_rng();
+    let occupied = positions.iter().copied().collect::<Vec<Position>>();
     let all_position = all_position();
-    let position = all_position
-        .iter()
-        .filter(|pos| !positions.iter().any(|p| p == *pos))
-        .choose(&mut rand::thread_rng());
+    let mut taken = occupied.clone();
 
-    if let Some(position) = position {
+    // 普通紫色食物，每次都生成
+    if let Some(position) = all_position
+        .iter()
+        .filter(|pos| !taken.contains(pos))
+        .choose(&mut rng)
+    {
+        taken.push(*position);
         commands.spawn((
             SpriteBundle {
                 sprite: Sprite {
@@ -105,14 +123,59 @@ fn spawn_food(mut commands: Commands, positions: Query<&Position>) {
             Food,
         ));
     }
+
+    // 偶尔（1/N）再生成一个限时金色食物
+    if rng.gen_range(0..GOLDEN_FOOD_ODDS) == 0 {
+        if let Some(position) = all_position
+            .iter()
+            .filter(|pos| !taken.contains(pos))
+            .choose(&mut rng)
+        {
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: GOLDEN_FOOD_COLOR,
+                        ..default()
+                    },
+                    ..default()
+                },
+                *position,
+                GridSize::square(0.8),
+                Food,
+                GoldenFood,
+                Lifetime(Timer::from_seconds(
+                    GOLDEN_FOOD_LIFETIME_SECS,
+                    TimerMode::Once,
+                )),
+            ));
+        }
+    }
+}
+
+// 金色食物到期未吃掉则消失
+fn golden_food_lifetime(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut golden_foods: Query<(Entity, &mut Lifetime), With<GoldenFood>>,
+) {
+    for (food_id, mut lifetime) in golden_foods.iter_mut() {
+        if lifetime.0.tick(time.delta()).finished() {
+            commands.entity(food_id).despawn();
+        }
+    }
 }
 
+// snake_movement 每150ms执行一次，而 snake_movement_input 每帧都会执行，
+// 所以单个tick内可能连按两个方向键把蛇头掉头撞上脖子。
+// 用已提交的 direction 加一个最多两格的转向缓冲来彻底避免。
+const TURN_BUFFER_CAP: usize = 2;
+
 #[derive(Component)]
 struct SnakeHead {
-    dir: Direction,
-    // snake_movement 每150ms执行一次，snake_movement_input执行了多次
-    // 所以在snake_movement之前，可能导致蛇头在动之前反向了
-    moved: bool,
+    // 上一次在 snake_movement 中实际应用的朝向
+    direction: Direction,
+    // 等待应用的转向，FIFO，最多 TURN_BUFFER_CAP 个
+    buffer: VecDeque<Direction>,
 }
 
 #[derive(Component)]
@@ -138,11 +201,151 @@ impl DerefMut for SnakeSegments {
 #[derive(Resource, Default)]
 struct LastTailPosition(Option<Position>);
 
+// 本局得分，重开时归零
+#[derive(Resource, Default)]
+struct Score(u32);
+
+// 历史最高分，落盘保存
+#[derive(Resource, Default)]
+struct HighScore(u32);
+
+const HIGH_SCORE_FILE: &str = "highscore.txt";
+
+// 墙壁规则：Solid 撞墙即死，Wrap 从对侧穿出
+#[derive(Resource, Default, Clone, Copy, PartialEq)]
+enum WallMode {
+    #[default]
+    Solid,
+    Wrap,
+}
+
+#[derive(Component)]
+struct ScoreText;
+
+// 蛇越长走得越快：每次生长把周期缩短 step_ms，夹在 floor_ms 之上
+#[derive(Resource)]
+struct MovementTimer {
+    timer: Timer,
+    base_ms: u64,
+    step_ms: u64,
+    floor_ms: u64,
+}
+
+impl Default for MovementTimer {
+    fn default() -> Self {
+        let base_ms = 150;
+        Self {
+            timer: Timer::new(Duration::from_millis(base_ms), TimerMode::Repeating),
+            base_ms,
+            step_ms: 5,
+            floor_ms: 60,
+        }
+    }
+}
+
+impl MovementTimer {
+    // 按当前蛇长重新计算并应用移动周期
+    fn retune(&mut self, len: usize) {
+        let shrink = self.step_ms * len.saturating_sub(1) as u64;
+        let period = self.base_ms.saturating_sub(shrink).max(self.floor_ms);
+        self.timer.set_duration(Duration::from_millis(period));
+    }
+}
+
+// 作为 snake_movement 的运行条件：每帧推进计时器，到点才放行
+fn movement_timer_ready(time: Res<Time>, mut movement_timer: ResMut<MovementTimer>) -> bool {
+    movement_timer.timer.tick(time.delta()).just_finished()
+}
+
+fn reset_movement_speed(mut movement_timer: ResMut<MovementTimer>) {
+    *movement_timer = MovementTimer::default();
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    score.0 = 0;
+}
+
+// 启动时从磁盘读取最高分，文件不存在或损坏则保持 0
+fn load_high_score(mut high_score: ResMut<HighScore>) {
+    if let Ok(contents) = std::fs::read_to_string(HIGH_SCORE_FILE) {
+        if let Ok(value) = contents.trim().parse() {
+            high_score.0 = value;
+        }
+    }
+}
+
+// 游戏结束时若刷新记录则写回磁盘
+fn save_high_score(score: Res<Score>, mut high_score: ResMut<HighScore>) {
+    if score.0 > high_score.0 {
+        high_score.0 = score.0;
+        let _ = std::fs::write(HIGH_SCORE_FILE, high_score.0.to_string());
+    }
+}
+
+fn spawn_score_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Score: 0  High: 0",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(5.0),
+            ..default()
+        }),
+        ScoreText,
+    ));
+}
+
+fn update_score_text(
+    score: Res<Score>,
+    high_score: Res<HighScore>,
+    mut texts: Query<&mut Text, With<ScoreText>>,
+) {
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = format!("Score: {}  High: {}", score.0, high_score.0);
+    }
+}
+
+fn retune_movement_speed(
+    mut movement_timer: ResMut<MovementTimer>,
+    segments: Res<SnakeSegments>,
+    mut growth_reader: EventReader<GrowthEvent>,
+) {
+    if growth_reader.read().next().is_some() {
+        movement_timer.retune(segments.len());
+    }
+}
+
 #[derive(Event)]
-struct GrowthEvent;
+struct GrowthEvent {
+    amount: u32,
+}
 #[derive(Event)]
 struct GameOver;
 
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+enum AppState {
+    #[default]
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+// 标记各个界面的覆盖层节点，便于在 OnExit 时整屏清理
+#[derive(Component)]
+struct MenuUi;
+#[derive(Component)]
+struct MenuText;
+#[derive(Component)]
+struct GameOverUi;
+
 #[derive(PartialEq, Clone, Copy, Debug)]
 enum Direction {
     Up,
@@ -175,8 +378,8 @@ fn spawn_snake(mut commands: Commands, mut segments: ResMut<SnakeSegments>) {
             Position { x: 5, y: 5 },
             GridSize::square(0.8),
             SnakeHead {
-                dir: Direction::Up,
-                moved: false,
+                direction: Direction::Up,
+                buffer: VecDeque::new(),
             },
         ))
         .id();
@@ -203,21 +406,23 @@ fn spawn_snake_segment(mut commands: Commands, position: Position) -> Entity {
 
 fn snake_movement_input(inputs: Res<ButtonInput<KeyCode>>, mut heads: Query<&mut SnakeHead>) {
     let mut head = heads.single_mut();
-    let dir = if inputs.pressed(KeyCode::ArrowUp) {
+    let dir = if inputs.just_pressed(KeyCode::ArrowUp) {
         Direction::Up
-    } else if inputs.pressed(KeyCode::ArrowDown) {
+    } else if inputs.just_pressed(KeyCode::ArrowDown) {
         Direction::Down
-    } else if inputs.pressed(KeyCode::ArrowLeft) {
+    } else if inputs.just_pressed(KeyCode::ArrowLeft) {
         Direction::Left
-    } else if inputs.pressed(KeyCode::ArrowRight) {
+    } else if inputs.just_pressed(KeyCode::ArrowRight) {
         Direction::Right
     } else {
-        head.dir
+        return;
     };
 
-    if head.moved && dir != head.dir && dir != head.dir.opposite() {
-        head.dir = dir;
-        head.moved = false;
+    // 与缓冲区最后一个转向比较，缓冲为空时与已提交的 direction 比较，
+    // 既不能原地不动也不能掉头，满足才入队。
+    let last = head.buffer.back().copied().unwrap_or(head.direction);
+    if dir != last && dir != last.opposite() && head.buffer.len() < TURN_BUFFER_CAP {
+        head.buffer.push_back(dir);
     }
 }
 
@@ -226,6 +431,7 @@ fn snake_movement(
     mut last_tail_position: ResMut<LastTailPosition>,
     mut heads: Query<(Entity, &mut SnakeHead)>,
     mut positions: Query<&mut Position>,
+    wall_mode: Res<WallMode>,
     mut game_over_writer: EventWriter<GameOver>,
 ) {
     let segments_positions = segments
@@ -233,22 +439,32 @@ fn snake_movement(
         .map(|segment_id| *positions.get(*segment_id).unwrap())
         .collect::<Vec<Position>>();
     let (head_id, mut head) = heads.single_mut();
+    if let Some(next) = head.buffer.pop_front() {
+        head.direction = next;
+    }
     let mut head_pos = positions.get_mut(head_id).unwrap();
 
-    match head.dir {
+    match head.direction {
         Direction::Up => head_pos.y += 1,
         Direction::Down => head_pos.y -= 1,
         Direction::Left => head_pos.x -= 1,
         Direction::Right => head_pos.x += 1,
     }
-    head.moved = true;
 
-    if head_pos.x < 0
-        || head_pos.x >= ARENA_WIDTH as i32
-        || head_pos.y < 0
-        || head_pos.y >= ARENA_WIDTH as i32
-    {
-        game_over_writer.send(GameOver);
+    match *wall_mode {
+        WallMode::Solid => {
+            if head_pos.x < 0
+                || head_pos.x >= ARENA_WIDTH
+                || head_pos.y < 0
+                || head_pos.y >= ARENA_HEIGHT
+            {
+                game_over_writer.send(GameOver);
+            }
+        }
+        WallMode::Wrap => {
+            head_pos.x = head_pos.x.rem_euclid(ARENA_WIDTH);
+            head_pos.y = head_pos.y.rem_euclid(ARENA_HEIGHT);
+        }
     }
 
     if segments_positions.contains(&head_pos) {
@@ -266,46 +482,174 @@ fn snake_movement(
 
 fn snake_eating(
     mut commands: Commands,
-    foods: Query<(Entity, &Position), With<Food>>,
+    foods: Query<(Entity, &Position, Option<&GoldenFood>), With<Food>>,
     head_position: Query<&Position, With<SnakeHead>>,
+    mut score: ResMut<Score>,
     mut growth_wirter: EventWriter<GrowthEvent>,
 ) {
     let head_position = head_position.single();
-    for (food_id, food_position) in foods.iter() {
+    for (food_id, food_position, golden) in foods.iter() {
         if food_position == head_position {
             commands.entity(food_id).despawn();
-            growth_wirter.send(GrowthEvent);
+            let amount = if golden.is_some() {
+                GOLDEN_FOOD_GROWTH
+            } else {
+                1
+            };
+            score.0 += amount;
+            growth_wirter.send(GrowthEvent { amount });
         }
     }
 }
 
 fn snake_growth(
-    commands: Commands,
+    mut commands: Commands,
     last_tail_position: ResMut<LastTailPosition>,
     mut segments: ResMut<SnakeSegments>,
     mut growth_reader: EventReader<GrowthEvent>,
 ) {
-    if growth_reader.read().next().is_some() {
+    let total = growth_reader.read().map(|event| event.amount).sum::<u32>();
+    if total > 0 {
         let position = last_tail_position.0.unwrap();
-        let segment_id = spawn_snake_segment(commands, position);
-        segments.push(segment_id);
+        for _ in 0..total {
+            let segment_id = spawn_snake_segment(commands.reborrow(), position);
+            segments.push(segment_id);
+        }
     }
 }
 
 fn game_over(
-    mut commands: Commands,
     mut game_over_reader: EventReader<GameOver>,
-    segments: ResMut<SnakeSegments>,
-    foods: Query<Entity, With<Food>>,
+    mut next_state: ResMut<NextState<AppState>>,
 ) {
     if game_over_reader.read().next().is_some() {
-        foods.iter().for_each(|id| commands.entity(id).despawn());
-        segments
-            .iter()
-            .for_each(|id| commands.entity(*id).despawn());
+        info("游戏结束");
+        next_state.set(AppState::GameOver);
+    }
+}
 
-        info("游戏结束，重新开始");
-        spawn_snake(commands, segments);
+// 离开 Playing 时清理蛇身和所有食物
+fn teardown_game(
+    mut commands: Commands,
+    segments: Res<SnakeSegments>,
+    foods: Query<Entity, With<Food>>,
+) {
+    foods.iter().for_each(|id| commands.entity(id).despawn());
+    segments
+        .iter()
+        .for_each(|id| commands.entity(*id).despawn());
+}
+
+fn spawn_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            MenuUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "Snake\n\nPress Space to start",
+                    TextStyle {
+                        font_size: 48.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                MenuText,
+            ));
+        });
+}
+
+// 在菜单里用 W 键切换墙壁规则
+fn toggle_wall_mode(inputs: Res<ButtonInput<KeyCode>>, mut wall_mode: ResMut<WallMode>) {
+    if inputs.just_pressed(KeyCode::KeyW) {
+        *wall_mode = match *wall_mode {
+            WallMode::Solid => WallMode::Wrap,
+            WallMode::Wrap => WallMode::Solid,
+        };
+    }
+}
+
+fn update_menu_text(wall_mode: Res<WallMode>, mut texts: Query<&mut Text, With<MenuText>>) {
+    let walls = match *wall_mode {
+        WallMode::Solid => "Solid",
+        WallMode::Wrap => "Wrap",
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value =
+            format!("Snake\n\nPress Space to start\nWalls: {walls}  (W to toggle)");
+    }
+}
+
+fn spawn_game_over(mut commands: Commands, score: Res<Score>, high_score: Res<HighScore>) {
+    let message = format!(
+        "Game Over\n\nScore: {}  High: {}\n\nPress Space to restart",
+        score.0, high_score.0
+    );
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GameOverUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn despawn_ui<T: Component>(mut commands: Commands, ui: Query<Entity, With<T>>) {
+    ui.iter().for_each(|id| commands.entity(id).despawn_recursive());
+}
+
+// 在菜单/结束界面按空格开始游戏
+fn start_game(
+    inputs: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if inputs.just_pressed(KeyCode::Space) {
+        next_state.set(AppState::Playing);
+    }
+}
+
+// 游戏进行中用 Escape 在 Playing/Paused 之间切换
+fn toggle_pause(
+    inputs: Res<ButtonInput<KeyCode>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if inputs.just_pressed(KeyCode::Escape) {
+        match state.get() {
+            AppState::Playing => next_state.set(AppState::Paused),
+            AppState::Paused => next_state.set(AppState::Playing),
+            _ => {}
+        }
     }
 }
 
@@ -314,6 +658,10 @@ fn main() {
         .insert_resource(ClearColor(Color::srgb(0.04, 0.04, 0.04)))
         .insert_resource(SnakeSegments::default())
         .insert_resource(LastTailPosition::default())
+        .insert_resource(MovementTimer::default())
+        .insert_resource(Score::default())
+        .insert_resource(HighScore::default())
+        .insert_resource(WallMode::default())
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: " Snake".into(),
@@ -323,20 +671,58 @@ fn main() {
             ..default()
         }))
         .add_plugins(FpsPlugin)
-        .add_systems(Startup, spawn_camera)
-        .add_systems(Update, (size_scaling, position_translation))
-        .add_systems(Startup, (spawn_snake, spawn_food))
+        .init_state::<AppState>()
+        .add_systems(Startup, (spawn_camera, load_high_score, spawn_score_text))
+        .add_systems(Update, (size_scaling, position_translation, update_score_text))
+        // 菜单 / 结束界面的进出与重开
+        .add_systems(OnEnter(AppState::Menu), spawn_menu)
+        .add_systems(
+            OnExit(AppState::Menu),
+            (
+                despawn_ui::<MenuUi>,
+                reset_movement_speed,
+                reset_score,
+                spawn_snake,
+                spawn_food,
+            ),
+        )
+        .add_systems(
+            OnEnter(AppState::GameOver),
+            (save_high_score, spawn_game_over, teardown_game).chain(),
+        )
+        .add_systems(
+            OnExit(AppState::GameOver),
+            (
+                despawn_ui::<GameOverUi>,
+                reset_movement_speed,
+                reset_score,
+                spawn_snake,
+                spawn_food,
+            ),
+        )
+        .add_systems(
+            Update,
+            start_game.run_if(in_state(AppState::Menu).or_else(in_state(AppState::GameOver))),
+        )
         .add_systems(
             Update,
-            snake_movement.run_if(on_timer(Duration::from_millis(150))),
+            (toggle_wall_mode, update_menu_text).run_if(in_state(AppState::Menu)),
         )
-        .add_systems(Update, snake_movement_input.before(snake_movement))
-        .add_systems(Update, game_over.after(snake_movement))
-        .add_systems(Update, snake_eating.after(snake_movement))
-        .add_systems(Update, snake_growth.after(snake_eating))
+        .add_systems(Update, toggle_pause)
+        // 核心玩法仅在 Playing 状态运行
         .add_systems(
             Update,
-            spawn_food.run_if(on_timer(Duration::from_millis(1500))),
+            (
+                snake_movement_input.before(snake_movement),
+                snake_movement.run_if(movement_timer_ready),
+                game_over.after(snake_movement),
+                snake_eating.after(snake_movement),
+                snake_growth.after(snake_eating),
+                retune_movement_speed.after(snake_growth),
+                golden_food_lifetime,
+                spawn_food.run_if(on_timer(Duration::from_millis(1500))),
+            )
+                .run_if(in_state(AppState::Playing)),
         )
         .add_event::<GrowthEvent>()
         .add_event::<GameOver>()